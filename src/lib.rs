@@ -6,19 +6,130 @@
 // copied, modified, or distributed except according to those terms.
 extern crate num;
 extern crate byteorder;
+extern crate rand;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
 use std::time;
 use self::num::BigUint;
-use self::byteorder::{LittleEndian, WriteBytesExt};
+use self::byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use self::rand::RngCore;
 
 #[derive(Debug)]
 pub enum FlakeError {
-    ClockIsRunningBackwards
+    ClockIsRunningBackwards,
+    InvalidEncoding,
+    InvalidConfiguration,
+    SequenceExhausted,
+    Io(std::io::Error),
+}
+
+/// Number of times `update` will busy-poll `current_flake_time_ms` while
+/// waiting for the clock to advance past a millisecond whose sequence
+/// counter has been exhausted, before giving up with
+/// `FlakeError::SequenceExhausted`.
+const SEQUENCE_SPIN_LIMIT: u32 = 10_000_000;
+
+/// Default width, in bits, of the timestamp field packed into an ID.
+const DEFAULT_TIMESTAMP_BITS: u8 = 64;
+
+/// Default width, in bits, of the worker identifier field packed into an ID.
+const DEFAULT_WORKER_BITS: u8 = 48;
+
+/// Default width, in bits, of the sequence counter field packed into an ID.
+const DEFAULT_SEQ_BITS: u8 = 16;
+
+/// Total width, in bits, of a generated ID. `timestamp_bits`, `worker_bits`,
+/// and `seq_bits` must always sum to this.
+const TOTAL_ID_BITS: u32 = 128;
+
+/// The alphabet used for base62 encoding, ordered so that byte-wise string
+/// comparison matches numeric order.
+const BASE62_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The width, in characters, of a base62-encoded 128-bit flake ID.
+///
+/// Base62 is not fixed-width, so encoded output is left-padded with the
+/// alphabet's zero character to this width. This keeps lexical ordering of
+/// the strings consistent with the numeric ordering of the underlying
+/// `BigUint` values.
+const BASE62_WIDTH: usize = 22;
+
+/// Encodes a `BigUint` as a base62 string using the alphabet `0-9A-Za-z`.
+///
+/// The digit, most-significant first, is computed by repeatedly dividing
+/// `value` by 62 and mapping the remainder to `BASE62_ALPHABET`. The result
+/// is left-padded with `0` to `BASE62_WIDTH` characters so that sorting the
+/// strings matches sorting the original 128-bit values.
+pub fn encode_base62(value: &BigUint) -> String {
+    let base = BigUint::from(62u32);
+    let mut remaining = value.clone();
+    let mut digits = Vec::new();
+
+    if remaining == BigUint::from(0u32) {
+        digits.push(BASE62_ALPHABET[0]);
+    }
+
+    while remaining > BigUint::from(0u32) {
+        let remainder = (&remaining % &base).to_bytes_le();
+        let idx = if remainder.is_empty() { 0 } else { remainder[0] as usize };
+        digits.push(BASE62_ALPHABET[idx]);
+        remaining /= &base;
+    }
+
+    digits.reverse();
+
+    let mut encoded = String::with_capacity(BASE62_WIDTH);
+    for _ in digits.len()..BASE62_WIDTH {
+        encoded.push(BASE62_ALPHABET[0] as char);
+    }
+    for b in digits {
+        encoded.push(b as char);
+    }
+
+    encoded
+}
+
+/// Decodes a base62 string, as produced by `encode_base62`, back into a
+/// `BigUint`.
+///
+/// Each character's index into `BASE62_ALPHABET` is folded into an
+/// accumulator via `acc = acc * 62 + idx`. Returns `Err` if `input`
+/// contains any character outside the base62 alphabet.
+pub fn decode_base62(input: &str) -> Result<BigUint, FlakeError> {
+    let base = BigUint::from(62u32);
+    let mut acc = BigUint::from(0u32);
+
+    for c in input.bytes() {
+        let idx = BASE62_ALPHABET.iter().position(|&a| a == c)
+            .ok_or(FlakeError::InvalidEncoding)?;
+        acc = acc * &base + BigUint::from(idx as u32);
+    }
+
+    Ok(acc)
 }
 
 pub struct Flaker {
     identifier: [u8; 6],
     last_generated_time_ms: u64,
     counter: u16,
+    epoch: u64,
+    timestamp_bits: u8,
+    worker_bits: u8,
+    seq_bits: u8,
+    start_instant: time::Instant,
+    start_ts: u64,
+    persist_path: Option<PathBuf>,
+    last_flushed_time_ms: u64,
+}
+
+impl Default for Flaker {
+    /// Returns a `Flaker` with a zeroed identifier and the default 64/48/16
+    /// bit layout. Intended as a starting point for the `epoch`/`bitwidths`
+    /// builder methods; prefer `new` or `new_from_identifier` for an
+    /// actual worker identifier.
+    fn default() -> Flaker {
+        Flaker::new([0u8; 6], Endianness::LittleEndian)
+    }
 }
 
 #[derive(PartialEq)]
@@ -62,12 +173,125 @@ impl Flaker {
             identifier.reverse();
         }
 
+        let start_ts = Flaker::current_time_in_ms();
+
         Flaker { identifier: identifier,
-                last_generated_time_ms: Flaker::current_time_in_ms(),
-                counter: 0
+                last_generated_time_ms: start_ts,
+                counter: 0,
+                epoch: 0,
+                timestamp_bits: DEFAULT_TIMESTAMP_BITS,
+                worker_bits: DEFAULT_WORKER_BITS,
+                seq_bits: DEFAULT_SEQ_BITS,
+                start_instant: time::Instant::now(),
+                start_ts,
+                persist_path: None,
+                last_flushed_time_ms: 0,
                 }
     }
 
+    /// Creates a new `Flaker` that persists its last-used timestamp to
+    /// `path`, guarding against a restarted process minting IDs it has
+    /// already issued.
+    ///
+    /// If `path` exists, its previously persisted timestamp is compared
+    /// against the current time; if the current time is earlier (a clock
+    /// that has stepped backward, or a worker identifier reused on a
+    /// machine with stale state), this returns
+    /// `FlakeError::ClockIsRunningBackwards` instead of starting.
+    /// Otherwise `update` periodically flushes `last_generated_time_ms`
+    /// back to `path`, so a future restart can perform the same check.
+    pub fn init_from_path<P: AsRef<Path>>(identifier: [u8; 6], path: P) -> Result<Flaker, FlakeError> {
+        let path = path.as_ref().to_path_buf();
+        let current_time_in_ms = Flaker::current_time_in_ms();
+
+        let persisted = Flaker::read_persisted_timestamp(&path)?;
+        if persisted.is_some_and(|p| p > current_time_in_ms) {
+            return Err(FlakeError::ClockIsRunningBackwards);
+        }
+
+        let mut flaker = Flaker::new(identifier, Endianness::LittleEndian);
+        flaker.persist_path = Some(path);
+        flaker.flush_persisted_timestamp()?;
+        flaker.last_flushed_time_ms = flaker.last_generated_time_ms;
+
+        Ok(flaker)
+    }
+
+    /// Reads a timestamp previously written by `flush_persisted_timestamp`,
+    /// or `None` if `path` does not exist yet.
+    fn read_persisted_timestamp(path: &Path) -> Result<Option<u64>, FlakeError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut file = File::open(path).map_err(FlakeError::Io)?;
+        let timestamp = file.read_u64::<LittleEndian>().map_err(FlakeError::Io)?;
+
+        Ok(Some(timestamp))
+    }
+
+    /// Writes `last_generated_time_ms` to `persist_path`, if one was set
+    /// via `init_from_path`.
+    ///
+    /// The new value is written to a sibling `.tmp` file and then renamed
+    /// over `persist_path`, so a crash mid-write leaves the previously
+    /// persisted timestamp intact instead of a truncated, unreadable file.
+    fn flush_persisted_timestamp(&self) -> Result<(), FlakeError> {
+        if let Some(ref path) = self.persist_path {
+            let mut tmp_path = path.clone().into_os_string();
+            tmp_path.push(".tmp");
+            let tmp_path = PathBuf::from(tmp_path);
+
+            {
+                let mut file = File::create(&tmp_path).map_err(FlakeError::Io)?;
+                file.write_u64::<LittleEndian>(self.last_generated_time_ms).map_err(FlakeError::Io)?;
+            }
+
+            fs::rename(&tmp_path, path).map_err(FlakeError::Io)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets a custom epoch, in milliseconds since the UNIX epoch, that is
+    /// subtracted from the current time before it is packed into generated
+    /// IDs.
+    ///
+    /// Choosing an epoch closer to the present reclaims high timestamp
+    /// bits, extending how long a deployment can mint IDs before the
+    /// timestamp field overflows.
+    pub fn epoch(mut self, epoch_ms: u64) -> Flaker {
+        self.epoch = epoch_ms;
+        self
+    }
+
+    /// Configures the bit widths of the timestamp, worker identifier, and
+    /// sequence counter fields packed into each generated ID, in place of
+    /// the default 64/48/16 split.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FlakeError::InvalidConfiguration` if the three widths do
+    /// not sum to `TOTAL_ID_BITS`, or if this `Flaker`'s worker identifier
+    /// does not fit within `worker_bits`.
+    pub fn bitwidths(mut self, timestamp_bits: u8, worker_bits: u8, seq_bits: u8) -> Result<Flaker, FlakeError> {
+        let total = timestamp_bits as u32 + worker_bits as u32 + seq_bits as u32;
+        if total != TOTAL_ID_BITS {
+            return Err(FlakeError::InvalidConfiguration);
+        }
+
+        let worker_value = BigUint::from_bytes_le(&self.identifier);
+        if worker_value.bits() as u32 > worker_bits as u32 {
+            return Err(FlakeError::InvalidConfiguration);
+        }
+
+        self.timestamp_bits = timestamp_bits;
+        self.worker_bits = worker_bits;
+        self.seq_bits = seq_bits;
+
+        Ok(self)
+    }
+
     /// Returns the current UNIX time in milliseconds
     fn current_time_in_ms() -> u64 {
         let now_ts = match time::SystemTime::now().duration_since(time::UNIX_EPOCH) {
@@ -80,41 +304,135 @@ impl Flaker {
         // Then, get the current time as milliseconds.
         now_ts.as_secs() * 1000 + (now_ts.subsec_nanos() / 1000_000) as u64
     }
-    
+
+    /// Returns the current flake time in milliseconds, derived from the
+    /// monotonic `start_instant` baseline rather than `SystemTime` directly.
+    ///
+    /// Because `Instant` is guaranteed never to go backwards, this keeps
+    /// generated IDs monotonic for the life of this `Flaker` even across
+    /// small backward jumps of the wall clock (e.g. an NTP step), while
+    /// still anchoring the value to wall-clock time via `start_ts`.
+    fn current_flake_time_ms(&self) -> u64 {
+        self.start_ts + self.start_instant.elapsed().as_millis() as u64
+    }
+
     /// Creates a new flake ID from the identifier, current time, and an internal counter.
-    /// Identifiers are generated as 128-bit numbers:
-    /// * 64-bit timestamp as milliseconds since the dawn of time (January 1, 1970)
-    /// * 48-bit worker identifier
-    /// * 16-bit sequence number that is incremented when more than one identifier is requested in the same millisecond and reset to 0 when the clock moves forward
+    /// Identifiers are generated as `TOTAL_ID_BITS`-bit numbers, packed
+    /// most-significant field first according to the configured layout:
+    /// * `timestamp_bits` milliseconds elapsed since `self.epoch`
+    /// * `worker_bits` worker identifier
+    /// * `seq_bits` sequence number that is incremented when more than one identifier is requested in the same millisecond and reset to 0 when the clock moves forward
     fn construct_id(&mut self) -> BigUint {
-        // Create a new slice of bytes
-        let mut bytes = [0 as u8; 16];
+        let timestamp_value = BigUint::from(self.last_generated_time_ms - self.epoch);
+        let worker_value = BigUint::from_bytes_le(&self.identifier);
+        let counter_value = BigUint::from(self.counter);
+
+        (timestamp_value << (self.worker_bits as usize + self.seq_bits as usize))
+            | (worker_value << self.seq_bits as usize)
+            | counter_value
+    }
+
+    /// Splits a previously generated flake `id` back into its timestamp,
+    /// worker identifier, and sequence counter, according to this
+    /// `Flaker`'s configured epoch and bit layout.
+    ///
+    /// This is the inverse of `construct_id`: the top `timestamp_bits` of
+    /// `id` are read as milliseconds elapsed since `self.epoch`, the next
+    /// `worker_bits` as the worker identifier, and the low `seq_bits` as
+    /// the counter.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FlakeError::InvalidEncoding` if `id` sets bits above
+    /// `TOTAL_ID_BITS`, if its counter does not fit in a `u16` or its
+    /// timestamp in a `u64`, or if adding `self.epoch` to the timestamp
+    /// overflows. An `id` minted under a different epoch or bit layout is
+    /// not detected; it decodes without error into different field values.
+    pub fn decode(&self, id: &BigUint) -> Result<(u64, [u8; 6], u16), FlakeError> {
+        self.decode_with_endianness(id, Endianness::LittleEndian)
+    }
 
-        // push the counter into bytes
-        bytes[0] = self.counter as u8;
-        bytes[1] = (self.counter >> 8) as u8;
+    /// Like `decode`, but reverses the worker identifier bytes back to big
+    /// endian order when `endian` is `Endianness::BigEndian`.
+    ///
+    /// Use this when the `Flaker` that generated `id` was constructed with
+    /// `Endianness::BigEndian`, since `new` reverses the identifier bytes
+    /// on the way in.
+    ///
+    /// # Errors
+    ///
+    /// Same as `decode`.
+    pub fn decode_with_endianness(&self, id: &BigUint, endian: Endianness) -> Result<(u64, [u8; 6], u16), FlakeError> {
+        use self::num::ToPrimitive;
 
-        // next 6 bytes are the worker id
-        for (pos, byte) in self.identifier.iter().enumerate() {
-            bytes[pos + 2] = *byte;
+        if id.bits() as u32 > TOTAL_ID_BITS {
+            return Err(FlakeError::InvalidEncoding);
         }
 
-        let mut wtr = vec![];
+        let seq_bits = self.seq_bits as usize;
+        let worker_bits = self.worker_bits as usize;
 
-        wtr.write_u64::<LittleEndian>(self.last_generated_time_ms).unwrap();
+        let seq_mask = (BigUint::from(1u32) << seq_bits) - BigUint::from(1u32);
+        let worker_mask = (BigUint::from(1u32) << worker_bits) - BigUint::from(1u32);
 
-        // fill the rest of the buffer with the current time, as bytes
-        for (pos, w) in wtr.into_iter().enumerate() {
-            bytes[pos + 8] = w;
+        let counter = (id.clone() & seq_mask).to_u64().ok_or(FlakeError::InvalidEncoding)?;
+        if counter > u64::from(u16::MAX) {
+            return Err(FlakeError::InvalidEncoding);
+        }
+
+        let worker_value = (id.clone() >> seq_bits) & worker_mask;
+        let mut identifier = [0u8; 6];
+        let worker_bytes = worker_value.to_bytes_le();
+        if worker_bytes.len() > identifier.len() {
+            return Err(FlakeError::InvalidEncoding);
+        }
+        identifier[..worker_bytes.len()].clone_from_slice(&worker_bytes);
+        if endian == Endianness::BigEndian {
+            identifier.reverse();
+        }
+
+        let timestamp_value = (id.clone() >> (seq_bits + worker_bits)).to_u64().ok_or(FlakeError::InvalidEncoding)?;
+        let timestamp = timestamp_value.checked_add(self.epoch).ok_or(FlakeError::InvalidEncoding)?;
+
+        Ok((timestamp, identifier, counter as u16))
+    }
+
+    /// The largest counter value that fits in the configured `seq_bits`.
+    fn max_counter(&self) -> u16 {
+        if self.seq_bits >= 16 {
+            u16::MAX
+        } else {
+            ((1u32 << self.seq_bits as u32) - 1) as u16
+        }
+    }
+
+    /// The largest number of milliseconds elapsed since `self.epoch` that
+    /// fits in the configured `timestamp_bits`.
+    fn max_timestamp(&self) -> u64 {
+        if self.timestamp_bits >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << self.timestamp_bits as u32) - 1
         }
-        
-        // create a BigUint from the buffer
-        BigUint::from_bytes_le(&bytes)
     }
 
     /// Update internal data structures.
+    ///
+    /// If `counter` has already reached `max_counter` for the current
+    /// millisecond, this busy-polls `current_flake_time_ms` until the clock
+    /// advances rather than letting the counter wrap and mint a duplicate
+    /// ID. `FlakeError::SequenceExhausted` is only returned if the clock
+    /// fails to advance within `SEQUENCE_SPIN_LIMIT` spins.
     fn update(&mut self) -> Result<(), FlakeError> {
-        let current_time_in_ms = Flaker::current_time_in_ms();
+        let mut current_time_in_ms = self.current_flake_time_ms();
+
+        if current_time_in_ms < self.epoch {
+            return Result::Err(FlakeError::InvalidConfiguration);
+        }
+
+        if current_time_in_ms - self.epoch > self.max_timestamp() {
+            return Result::Err(FlakeError::InvalidConfiguration);
+        }
 
         if self.last_generated_time_ms > current_time_in_ms {
             return Result::Err(FlakeError::ClockIsRunningBackwards);
@@ -123,19 +441,185 @@ impl Flaker {
         if self.last_generated_time_ms < current_time_in_ms {
             self.counter = 0;
         }
+        else if self.counter == self.max_counter() {
+            let mut spins = 0;
+            while current_time_in_ms <= self.last_generated_time_ms {
+                spins += 1;
+                if spins > SEQUENCE_SPIN_LIMIT {
+                    return Result::Err(FlakeError::SequenceExhausted);
+                }
+                current_time_in_ms = self.current_flake_time_ms();
+            }
+            self.counter = 0;
+        }
         else {
             self.counter += 1;
         }
 
         self.last_generated_time_ms = current_time_in_ms;
 
+        // Flush at most once per millisecond tick, not once per ID, so a
+        // burst of same-millisecond IDs doesn't pay a `File::create` +
+        // `write` on every call. A transient write failure here is not
+        // allowed to fail the mint: `counter`/`last_generated_time_ms`
+        // have already advanced in memory, so surfacing the error from
+        // `get_id` would burn an ID slot without actually un-minting it.
+        if self.last_generated_time_ms > self.last_flushed_time_ms && self.flush_persisted_timestamp().is_ok() {
+            self.last_flushed_time_ms = self.last_generated_time_ms;
+        }
+
         Ok(())
     }
 
-    /// Generate a new ID 
+    /// Generate a new ID
     pub fn get_id(&mut self) -> Result<BigUint, FlakeError> {
         self.update().map(|_| self.construct_id())
     }
+
+    /// Generate a new ID and render it as a base62 string.
+    ///
+    /// Base62 strings are more convenient than a raw `BigUint` for
+    /// embedding in URLs, database keys, or log lines, and the fixed-width
+    /// encoding preserves the k-ordering of the underlying IDs.
+    pub fn get_id_string(&mut self) -> Result<String, FlakeError> {
+        self.get_id().map(|id| encode_base62(&id))
+    }
+}
+
+/// The alphabet used for Crockford base32 encoding, which excludes the
+/// visually ambiguous letters `I`, `L`, `O`, and `U`.
+const CROCKFORD_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Width, in bits, of the ULID timestamp field.
+const ULID_TIMESTAMP_BITS: usize = 48;
+
+/// Width, in bits, of the ULID random field.
+const ULID_RANDOM_BITS: usize = 80;
+
+/// Width, in characters, of a Crockford base32-encoded ULID. 26 characters
+/// of 5 bits each cover 130 bits, 2 more than `ULID_TIMESTAMP_BITS +
+/// ULID_RANDOM_BITS`; those 2 extra high bits are always zero.
+const ULID_WIDTH: usize = 26;
+
+/// Encodes a 128-bit value as a 26-character Crockford base32 string, most
+/// significant 5-bit group first.
+pub fn encode_crockford32(value: &BigUint) -> String {
+    use self::num::ToPrimitive;
+
+    let mask = BigUint::from(0x1Fu32);
+
+    // `value` is ULID_TIMESTAMP_BITS + ULID_RANDOM_BITS = 128 bits wide, 2
+    // bits short of the 130 bits covered by 26 five-bit groups; the
+    // topmost group's high 2 bits come out zero since `value` simply has
+    // no bits there, so no explicit padding is needed.
+    let mut chars = Vec::with_capacity(ULID_WIDTH);
+    for i in (0..ULID_WIDTH).rev() {
+        let chunk = (value.clone() >> (i * 5)) & mask.clone();
+        chars.push(CROCKFORD_ALPHABET[chunk.to_u32().unwrap() as usize]);
+    }
+
+    chars.into_iter().map(|b| b as char).collect()
+}
+
+/// Decodes a Crockford base32 string, as produced by `encode_crockford32`,
+/// back into a `BigUint`.
+///
+/// Decoding is case-insensitive, and the visually ambiguous letters `I`
+/// and `L` are read as `1`, and `O` is read as `0`, matching how they are
+/// commonly mistyped. Returns `Err` if `input` is not `ULID_WIDTH`
+/// characters or contains a character outside the alphabet (including the
+/// deliberately excluded `U`).
+pub fn decode_crockford32(input: &str) -> Result<BigUint, FlakeError> {
+    if input.chars().count() != ULID_WIDTH {
+        return Err(FlakeError::InvalidEncoding);
+    }
+
+    let mut acc = BigUint::from(0u32);
+
+    for c in input.chars() {
+        let normalized = match c.to_ascii_uppercase() {
+            'I' | 'L' => '1',
+            'O' => '0',
+            other => other,
+        };
+
+        let idx = CROCKFORD_ALPHABET.iter().position(|&a| a == normalized as u8)
+            .ok_or(FlakeError::InvalidEncoding)?;
+
+        acc = (acc << 5) | BigUint::from(idx as u32);
+    }
+
+    Ok(acc)
+}
+
+/// Generates ULID-compatible identifiers: a 48-bit millisecond timestamp
+/// in the high bits followed by 80 bits of cryptographic randomness,
+/// rendered as Crockford base32. Unlike `Flaker`, the random component
+/// does not derive from a worker identifier, so generated IDs don't leak
+/// any machine-identifying information.
+pub struct UlidGenerator {
+    last_generated_time_ms: u64,
+    last_random: BigUint,
+    start_instant: time::Instant,
+    start_ts: u64,
+}
+
+impl Default for UlidGenerator {
+    fn default() -> UlidGenerator {
+        UlidGenerator::new()
+    }
+}
+
+impl UlidGenerator {
+    /// Returns a new `UlidGenerator` anchored to the current time.
+    pub fn new() -> UlidGenerator {
+        let start_ts = Flaker::current_time_in_ms();
+
+        UlidGenerator {
+            last_generated_time_ms: start_ts,
+            last_random: UlidGenerator::random_80_bits(),
+            start_instant: time::Instant::now(),
+            start_ts,
+        }
+    }
+
+    fn current_flake_time_ms(&self) -> u64 {
+        self.start_ts + self.start_instant.elapsed().as_millis() as u64
+    }
+
+    fn random_80_bits() -> BigUint {
+        let mut bytes = [0u8; ULID_RANDOM_BITS / 8];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        BigUint::from_bytes_be(&bytes)
+    }
+
+    /// Generates a new ULID as a `BigUint`.
+    ///
+    /// Within the same millisecond, the random component is incremented
+    /// by one, treating it as an 80-bit big integer, rather than being
+    /// re-randomized, which keeps IDs generated in the same millisecond
+    /// strictly sortable.
+    pub fn generate(&mut self) -> BigUint {
+        let current_time_in_ms = self.current_flake_time_ms();
+
+        if current_time_in_ms > self.last_generated_time_ms {
+            self.last_generated_time_ms = current_time_in_ms;
+            self.last_random = UlidGenerator::random_80_bits();
+        } else {
+            self.last_random = self.last_random.clone() + BigUint::from(1u32);
+        }
+
+        let timestamp_mask = (BigUint::from(1u32) << ULID_TIMESTAMP_BITS) - BigUint::from(1u32);
+        let timestamp_value = BigUint::from(self.last_generated_time_ms) & timestamp_mask;
+
+        (timestamp_value << ULID_RANDOM_BITS) | self.last_random.clone()
+    }
+
+    /// Generates a new ULID and renders it as a 26-character Crockford
+    /// base32 string.
+    pub fn generate_string(&mut self) -> String {
+        encode_crockford32(&self.generate())
+    }
 }
 
 #[test]
@@ -159,8 +643,240 @@ fn ids_change_quickly() {
 
     let id3 = f1.get_id().unwrap();
     let id4 = f1.get_id().unwrap();
-    
+
     println!("{} < {}", id3, id4);
 
     assert!(id3 < id4);
+}
+
+#[test]
+fn base62_round_trips() {
+    let mut f1 = Flaker::new_from_identifier(vec![0, 1, 2, 3, 4, 5]);
+    let id = f1.get_id().unwrap();
+
+    let encoded = encode_base62(&id);
+    assert_eq!(encoded.len(), BASE62_WIDTH);
+
+    let decoded = decode_base62(&encoded).unwrap();
+    assert_eq!(id, decoded);
+}
+
+#[test]
+fn base62_preserves_ordering() {
+    use std::time::Duration;
+    use std::thread;
+
+    let mut f1 = Flaker::new_from_identifier(vec![0, 1, 2, 3, 4, 5]);
+    let id1 = f1.get_id().unwrap();
+    thread::sleep(Duration::from_millis(5));
+    let id2 = f1.get_id().unwrap();
+
+    assert!(id1 < id2);
+    assert!(encode_base62(&id1) < encode_base62(&id2));
+}
+
+#[test]
+fn base62_rejects_invalid_characters() {
+    assert!(decode_base62("not-valid!!").is_err());
+}
+
+#[test]
+fn decode_recovers_generator_state() {
+    let identifier = [0, 1, 2, 3, 4, 5];
+    let mut f1 = Flaker::new(identifier, Endianness::LittleEndian);
+
+    let id = f1.get_id().unwrap();
+    let (timestamp, worker, counter) = f1.decode(&id).unwrap();
+
+    assert_eq!(timestamp, f1.last_generated_time_ms);
+    assert_eq!(worker, identifier);
+    assert_eq!(counter, f1.counter);
+}
+
+#[test]
+fn decode_rejects_an_id_with_bits_set_outside_the_configured_layout() {
+    let f1 = Flaker::new([0, 1, 2, 3, 4, 5], Endianness::LittleEndian);
+
+    let bogus_id = BigUint::from(1u32) << 200;
+
+    assert!(f1.decode(&bogus_id).is_err());
+}
+
+#[test]
+fn custom_bitwidths_and_epoch_round_trip() {
+    let identifier = [0, 1, 2, 3, 4, 5];
+    let custom_epoch = 1_700_000_000_000;
+
+    let mut f1 = Flaker::new(identifier, Endianness::LittleEndian)
+        .epoch(custom_epoch)
+        .bitwidths(40, 48, 40)
+        .unwrap();
+
+    let id = f1.get_id().unwrap();
+    let (timestamp, worker, _counter) = f1.decode(&id).unwrap();
+
+    assert_eq!(timestamp, f1.last_generated_time_ms);
+    assert_eq!(worker, identifier);
+}
+
+#[test]
+fn bitwidths_rejects_widths_not_summing_to_total() {
+    let result = Flaker::default().bitwidths(64, 48, 8);
+    assert!(result.is_err());
+}
+
+#[test]
+fn epoch_ahead_of_current_time_is_rejected_instead_of_underflowing() {
+    let mut f1 = Flaker::new([0, 1, 2, 3, 4, 5], Endianness::LittleEndian)
+        .epoch(9_999_999_999_999);
+
+    match f1.get_id() {
+        Err(FlakeError::InvalidConfiguration) => (),
+        other => panic!("expected InvalidConfiguration, got {:?}", other),
+    }
+}
+
+#[test]
+fn timestamp_wider_than_configured_bits_is_rejected_instead_of_overflowing() {
+    let mut f1 = Flaker::new([0, 1, 2, 3, 4, 5], Endianness::LittleEndian)
+        .bitwidths(8, 64, 56)
+        .unwrap();
+
+    match f1.get_id() {
+        Err(FlakeError::InvalidConfiguration) => (),
+        other => panic!("expected InvalidConfiguration, got {:?}", other),
+    }
+}
+
+#[test]
+fn sequence_counter_spins_and_rolls_over_on_exhaustion() {
+    let mut f1 = Flaker::new([0, 1, 2, 3, 4, 5], Endianness::LittleEndian);
+    f1.counter = f1.max_counter();
+
+    let id = f1.get_id().unwrap();
+    let (_, _, counter) = f1.decode(&id).unwrap();
+
+    assert_eq!(counter, 0);
+    assert!(f1.last_generated_time_ms > 0);
+}
+
+#[test]
+fn flake_time_is_monotonic_from_instant_baseline() {
+    let f1 = Flaker::new([0, 1, 2, 3, 4, 5], Endianness::LittleEndian);
+
+    let t1 = f1.current_flake_time_ms();
+    let t2 = f1.current_flake_time_ms();
+
+    assert!(t2 >= t1);
+}
+
+#[test]
+fn init_from_path_persists_and_checks_timestamp() {
+    use std::env;
+    use std::fs;
+
+    let path = env::temp_dir().join(format!("flaker_test_{}.ts", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut f1 = Flaker::init_from_path([0, 1, 2, 3, 4, 5], &path).unwrap();
+    let _id = f1.get_id().unwrap();
+
+    let persisted = Flaker::read_persisted_timestamp(&path).unwrap().unwrap();
+    assert_eq!(persisted, f1.last_generated_time_ms);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn flushed_timestamp_only_ever_catches_up_to_last_generated() {
+    use std::env;
+    use std::fs;
+
+    let path = env::temp_dir().join(format!("flaker_test_batch_{}.ts", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut f1 = Flaker::init_from_path([0, 1, 2, 3, 4, 5], &path).unwrap();
+    let mut last_flushed_seen = f1.last_flushed_time_ms;
+
+    for _ in 0..50 {
+        f1.get_id().unwrap();
+
+        // The flush is batched to at most once per distinct millisecond,
+        // so it should never run ahead of what was actually generated,
+        // and should never move backwards between calls.
+        assert!(f1.last_flushed_time_ms <= f1.last_generated_time_ms);
+        assert!(f1.last_flushed_time_ms >= last_flushed_seen);
+        last_flushed_seen = f1.last_flushed_time_ms;
+    }
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn init_from_path_rejects_stale_clock() {
+    use std::env;
+    use std::fs;
+
+    let path = env::temp_dir().join(format!("flaker_test_future_{}.ts", std::process::id()));
+    let future_ts = Flaker::current_time_in_ms() + 1_000_000;
+
+    {
+        let mut file = File::create(&path).unwrap();
+        file.write_u64::<LittleEndian>(future_ts).unwrap();
+    }
+
+    let result = Flaker::init_from_path([0, 1, 2, 3, 4, 5], &path);
+    assert!(result.is_err());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bitwidths_rejects_worker_id_too_large_for_width() {
+    let result = Flaker::new([255, 255, 255, 255, 255, 255], Endianness::LittleEndian)
+        .bitwidths(64, 8, 56);
+    assert!(result.is_err());
+}
+
+#[test]
+fn crockford32_round_trips() {
+    let mut ulid = UlidGenerator::new();
+    let id = ulid.generate();
+
+    let encoded = encode_crockford32(&id);
+    assert_eq!(encoded.len(), ULID_WIDTH);
+
+    let decoded = decode_crockford32(&encoded).unwrap();
+    assert_eq!(id, decoded);
+}
+
+#[test]
+fn crockford32_decoding_is_case_insensitive_and_maps_ambiguous_letters() {
+    let mut ulid = UlidGenerator::new();
+    let id = ulid.generate();
+    let encoded = encode_crockford32(&id);
+
+    let lowercased = encoded.to_lowercase();
+    assert_eq!(decode_crockford32(&lowercased).unwrap(), id);
+
+    let ambiguous = format!("{}iL", "O".repeat(ULID_WIDTH - 2));
+    let canonical = format!("{}11", "0".repeat(ULID_WIDTH - 2));
+    assert_eq!(decode_crockford32(&ambiguous).unwrap(), decode_crockford32(&canonical).unwrap());
+}
+
+#[test]
+fn crockford32_rejects_excluded_letter_u() {
+    let bad = "U".repeat(ULID_WIDTH);
+    assert!(decode_crockford32(&bad).is_err());
+}
+
+#[test]
+fn ulid_ids_increment_monotonically_within_same_millisecond() {
+    let mut ulid = UlidGenerator::new();
+
+    let id1 = ulid.generate();
+    let id2 = ulid.generate();
+
+    assert!(id2 > id1);
+    assert!(encode_crockford32(&id2) > encode_crockford32(&id1));
 }
\ No newline at end of file